@@ -1,19 +1,24 @@
-use std::str::FromStr;
-use std::io::{LineWriter,BufReader,BufRead};
+use std::io::{BufReader,BufRead};
 
 mod processor;
 
+use processor::{IoMode,StdinInput,StdoutOutput};
+
 fn main() {
     let line = std::io::stdin().lock().lines().next().unwrap().unwrap();
-    let mut reader = BufReader::new(std::io::stdin());
-    let mut writer = LineWriter::new(std::io::stdout());
+    let reader = BufReader::new(std::io::stdin());
+    let writer = std::io::stdout();
+
+    let program = processor::parse_program(&line).expect("malformed program");
 
-    let program = line
-        .split(',')
-        .map(FromStr::from_str)
-        .map(Result::unwrap)
-        .collect();
+    let mut processor = processor::Processor::initialize(
+        program,
+        StdinInput::new(reader),
+        StdoutOutput::new(writer),
+        IoMode::Streaming,
+    );
 
-    let mut processor = processor::Processor::initialize(program, &mut reader, &mut writer);
-    processor.run();
+    if let Err(fault) = processor.run() {
+        eprintln!("processor fault: {:?}", fault);
+    }
 }
@@ -1,14 +1,131 @@
+use std::cell::RefCell;
+use std::collections::{HashSet,VecDeque};
 use std::convert::TryFrom;
 use std::io::{BufRead,LineWriter,Write};
+use std::rc::Rc;
 use std::str::FromStr;
 
 use num_enum::TryFromPrimitive;
 
+/// A source of machine input. `read` yields the next value if one is
+/// available, while `push` feeds a value in for a later `read`.
+pub trait Input {
+    fn read(&mut self) -> Option<i64>;
+    fn push(&mut self, v: i64);
+}
+
+/// A sink for machine output.
+pub trait Output {
+    fn write(&mut self, v: i64);
+}
+
+/// A `VecDeque`-backed FIFO usable as both `Input` and `Output`, so one
+/// machine's output can be wired directly into another's input.
+#[derive(Debug, Default)]
+pub struct Pipe {
+    queue: VecDeque<i64>,
+}
+
+impl Pipe {
+    pub fn new() -> Pipe {
+        return Pipe { queue: VecDeque::new() }
+    }
+}
+
+impl Input for Pipe {
+    fn read(&mut self) -> Option<i64> {
+        return self.queue.pop_front();
+    }
+
+    fn push(&mut self, v: i64) {
+        self.queue.push_back(v);
+    }
+}
+
+impl Output for Pipe {
+    fn write(&mut self, v: i64) {
+        self.queue.push_back(v);
+    }
+}
+
+// Shared handles so several machines can wire one machine's output into
+// another's input, e.g. five amplifier stages sharing `Rc<RefCell<Pipe>>`.
+impl<T: Input> Input for Rc<RefCell<T>> {
+    fn read(&mut self) -> Option<i64> {
+        return self.borrow_mut().read();
+    }
+
+    fn push(&mut self, v: i64) {
+        self.borrow_mut().push(v);
+    }
+}
+
+impl<T: Output> Output for Rc<RefCell<T>> {
+    fn write(&mut self, v: i64) {
+        self.borrow_mut().write(v);
+    }
+}
+
+/// `Input` adapter over any `BufRead`, parsing one decimal value per line.
+pub struct StdinInput<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> StdinInput<R> {
+    pub fn new(reader: R) -> StdinInput<R> {
+        return StdinInput { reader: reader }
+    }
+}
+
+impl<R: BufRead> Input for StdinInput<R> {
+    fn read(&mut self) -> Option<i64> {
+        let mut line = String::new();
+        return match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => FromStr::from_str(line.trim()).ok(),
+            Err(_) => None,
+        }
+    }
+
+    fn push(&mut self, _v: i64) {
+        // stdin is read-only; there is nowhere to push a value back to.
+    }
+}
+
+/// `Output` adapter over any `Write`, emitting one decimal value per line.
+pub struct StdoutOutput<W: Write> {
+    writer: LineWriter<W>,
+}
+
+impl<W: Write> StdoutOutput<W> {
+    pub fn new(writer: W) -> StdoutOutput<W> {
+        return StdoutOutput { writer: LineWriter::new(writer) }
+    }
+}
+
+impl<W: Write> Output for StdoutOutput<W> {
+    fn write(&mut self, v: i64) {
+        writeln!(self.writer, "{}", v).unwrap();
+    }
+}
+
 #[derive(Debug, PartialEq, TryFromPrimitive)]
 #[repr(i32)]
 enum ParameterMode {
     Memory = 0,
     Immediate = 1,
+    Relative = 2,
+}
+
+impl ParameterMode {
+    /// The prefix used to render a parameter of this mode in disassembly.
+    fn sigil(&self) -> char {
+        return match self {
+            ParameterMode::Memory => '@',
+            ParameterMode::Immediate => '#',
+            ParameterMode::Relative => '~',
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, TryFromPrimitive)]
@@ -25,15 +142,99 @@ enum InstructionType {
     IsLessThan = 7,
     IsEqual = 8,
 
+    AdjustRelativeBase = 9,
+
     Halt = 99,
 }
 
-pub struct Processor<'a, W: Write> {
-    memory: Vec<i32>,
+impl InstructionType {
+    /// A short printable mnemonic for disassembly output.
+    fn mnemonic(&self) -> &'static str {
+        return match self {
+            InstructionType::Add => "ADD",
+            InstructionType::Multiply => "MUL",
+            InstructionType::Read => "IN",
+            InstructionType::Print => "OUT",
+            InstructionType::JumpNZ => "JNZ",
+            InstructionType::JumpZ => "JZ",
+            InstructionType::IsLessThan => "LT",
+            InstructionType::IsEqual => "EQ",
+            InstructionType::AdjustRelativeBase => "ARB",
+            InstructionType::Halt => "HLT",
+        }
+    }
+
+    /// Number of parameter words this instruction consumes after its opcode.
+    fn arity(&self) -> usize {
+        return match self {
+            InstructionType::Add
+            | InstructionType::Multiply
+            | InstructionType::IsLessThan
+            | InstructionType::IsEqual => 3,
+            InstructionType::JumpNZ | InstructionType::JumpZ => 2,
+            InstructionType::Read
+            | InstructionType::Print
+            | InstructionType::AdjustRelativeBase => 1,
+            InstructionType::Halt => 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RunState {
+    Halted,
+    NeedsInput,
+}
+
+/// A recoverable processor fault, returned from `run()` instead of panicking;
+/// callers inspect the fault and decide. Invalid opcodes, invalid parameter
+/// modes, immediate write targets and malformed programs are reported this
+/// way. Store addresses are not range-checked: a write to a negative or very
+/// large address panics or aborts rather than returning a fault.
+#[derive(Debug, PartialEq)]
+pub enum Fault {
+    InvalidOpcode(i64),
+    InvalidMode(i64),
+    ImmediateWriteTarget,
+    MalformedProgram,
+}
+
+enum Flow {
+    Continue,
+    Stop(RunState),
+}
+
+/// How the processor moves values across its I/O boundary.
+///
+/// `Streaming` reads and writes one value at a time, suitable for interactive
+/// callers. `Batched` parses the whole input up front and accumulates output,
+/// flushing it in a single pass on `Halt` or an explicit [`Processor::flush`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IoMode {
+    Streaming,
+    Batched,
+}
+
+/// Parse a comma-separated Intcode program into its initial memory image.
+pub fn parse_program(line: &str) -> Result<Vec<i64>, Fault> {
+    return line
+        .trim()
+        .split(',')
+        .map(|cell| i64::from_str(cell).map_err(|_| Fault::MalformedProgram))
+        .collect();
+}
+
+pub struct Processor<I: Input, O: Output> {
+    memory: Vec<i64>,
     ip: usize,
+    relative_base: i64,
+
+    input:  I,
+    output: O,
 
-    input:  &'a mut dyn BufRead,
-    output: &'a mut LineWriter<W>,
+    io_mode:    IoMode,
+    in_queue:   VecDeque<i64>,
+    out_buffer: Vec<i64>,
 }
 
 
@@ -45,25 +246,45 @@ struct Instruction {
     p3_mode: ParameterMode,
 }
 
-impl<'a, W: Write> Processor<'a, W> {
-    pub fn initialize(program: Vec<i32>, input: &'a mut dyn BufRead, output: &'a mut LineWriter<W>) -> Processor<'a, W> {
-        return Processor {
+impl<I: Input, O: Output> Processor<I, O> {
+    pub fn initialize(program: Vec<i64>, input: I, output: O, io_mode: IoMode) -> Processor<I, O> {
+        let mut processor = Processor {
             memory: program,
             ip:     0,
+            relative_base: 0,
 
             input:  input,
             output: output,
+
+            io_mode:    io_mode,
+            in_queue:   VecDeque::new(),
+            out_buffer: Vec::new(),
+        };
+
+        // In batched mode the decimal parsing of the whole input happens once
+        // up front, rather than a value at a time during execution.
+        if io_mode == IoMode::Batched {
+            while let Some(v) = processor.input.read() {
+                processor.in_queue.push_back(v);
+            }
         }
+
+        return processor;
     }
 
-    pub fn run(&mut self) {
-        while !self.step().unwrap() { }
+    pub fn run(&mut self) -> Result<RunState, Fault> {
+        loop {
+            match self.step()? {
+                Flow::Continue => {},
+                Flow::Stop(state) => return Ok(state),
+            }
+        }
     }
 
-    fn step(&mut self) -> Result<bool, String> {
-        let i_type = self.current_instruction().i_type;
+    fn step(&mut self) -> Result<Flow, Fault> {
+        let i_type = self.current_instruction()?.i_type;
 
-        let _ = match i_type {
+        return match i_type {
             InstructionType::Add => self.binary_operation(&|(p1, p2)| p1 + p2),
             InstructionType::Multiply => self.binary_operation(&|(p1, p2)| p1 * p2),
 
@@ -76,56 +297,78 @@ impl<'a, W: Write> Processor<'a, W> {
             InstructionType::IsLessThan => self.binary_operation(&|(p1, p2)| if p1 < p2 { 1 } else { 0 }),
             InstructionType::IsEqual => self.binary_operation(&|(p1, p2)| if p1 == p2 { 1 } else { 0 }),
 
-            InstructionType::Halt => return Ok(true),
-        }?;
+            InstructionType::AdjustRelativeBase => self.adjust_relative_base(),
 
-        return Ok(false);
+            InstructionType::Halt => {
+                self.flush();
+                Ok(Flow::Stop(RunState::Halted))
+            },
+        };
     }
 
-    fn binary_operation(&mut self, op: &dyn Fn((i32, i32)) -> i32) -> Result<(), String> {
-        let instruction = self.current_instruction();
+    fn binary_operation(&mut self, op: &dyn Fn((i64, i64)) -> i64) -> Result<Flow, Fault> {
+        let instruction = self.current_instruction()?;
 
         let p1 = self.get_parameter_with_mode(1, instruction.p1_mode);
         let p2 = self.get_parameter_with_mode(2, instruction.p2_mode);
 
         if instruction.p3_mode == ParameterMode::Immediate {
-            return Err("got immediate parameter mode for store address".to_string())
+            return Err(Fault::ImmediateWriteTarget)
         }
-        let p3 = self.get_parameter(3);
+        let p3 = self.get_store_address(3, instruction.p3_mode);
 
-        self.memory[p3 as usize] = op((p1, p2));
+        self.write_mem(p3, op((p1, p2)));
         self.ip += 4;
-        return Ok(());
+        return Ok(Flow::Continue);
     }
 
-    fn read(&mut self) -> Result<(), String> {
-        let instruction = self.current_instruction();
+    fn read(&mut self) -> Result<Flow, Fault> {
+        let instruction = self.current_instruction()?;
 
         if instruction.p1_mode == ParameterMode::Immediate {
-            return Err("got immediate parameter mode for store address".to_string())
+            return Err(Fault::ImmediateWriteTarget)
         }
-        let p1 = self.get_parameter(1);
+        let p1 = self.get_store_address(1, instruction.p1_mode);
 
-        let line = self.input.lines().next().unwrap().unwrap();
-        let input = FromStr::from_str(&line).unwrap();
+        let input = match self.next_input() {
+            Some(v) => v,
+            // No input available yet: suspend without advancing `ip` so the
+            // read is re-executed once the caller supplies more input.
+            None => return Ok(Flow::Stop(RunState::NeedsInput)),
+        };
 
-        self.memory[p1 as usize] = input;
+        self.write_mem(p1, input);
         self.ip += 2;
-        return Ok(())
+        return Ok(Flow::Continue)
     }
 
-    fn print(&mut self) -> Result<(), String> {
-        let instruction = self.current_instruction();
+    fn print(&mut self) -> Result<Flow, Fault> {
+        let instruction = self.current_instruction()?;
         let p1 = self.get_parameter_with_mode(1, instruction.p1_mode);
 
-        writeln!(self.output, "{}", p1).unwrap();
+        match self.io_mode {
+            IoMode::Streaming => self.output.write(p1),
+            IoMode::Batched => self.out_buffer.push(p1),
+        }
 
         self.ip += 2;
-        return Ok(())
+        return Ok(Flow::Continue)
     }
 
-    fn conditional_jump(&mut self, condition: &dyn Fn(i32) -> bool) -> Result<(), String> {
-        let instruction = self.current_instruction();
+    /// Flush any values accumulated in batched mode to the output in one pass.
+    /// A no-op in streaming mode, where output is written as it is produced.
+    pub fn flush(&mut self) {
+        for v in self.out_buffer.drain(..) {
+            self.output.write(v);
+        }
+    }
+
+    fn next_input(&mut self) -> Option<i64> {
+        return self.in_queue.pop_front().or_else(|| self.input.read());
+    }
+
+    fn conditional_jump(&mut self, condition: &dyn Fn(i64) -> bool) -> Result<Flow, Fault> {
+        let instruction = self.current_instruction()?;
         let p1 = self.get_parameter_with_mode(1, instruction.p1_mode);
         let p2 = self.get_parameter_with_mode(2, instruction.p2_mode);
 
@@ -135,39 +378,336 @@ impl<'a, W: Write> Processor<'a, W> {
             self.ip += 3;
         }
 
-        return Ok(())
+        return Ok(Flow::Continue)
+    }
+
+    fn adjust_relative_base(&mut self) -> Result<Flow, Fault> {
+        let instruction = self.current_instruction()?;
+        let p1 = self.get_parameter_with_mode(1, instruction.p1_mode);
+
+        self.relative_base += p1;
+
+        self.ip += 2;
+        return Ok(Flow::Continue)
+    }
+
+    fn current_instruction(&self) -> Result<Instruction, Fault> {
+        return self.decode(self.ip);
     }
 
-    fn current_instruction(&self) -> Instruction {
-        let instruction = self.memory[self.ip];
+    fn decode(&self, addr: usize) -> Result<Instruction, Fault> {
+        let instruction = self.read_mem(addr);
 
-        let i_type = InstructionType::try_from(instruction % 100)
-            .expect("invalid opcode");
-        let p1_mode = self.parse_mode((instruction / 100) % 10);
-        let p2_mode = self.parse_mode((instruction / 1000) % 10);
-        let p3_mode = self.parse_mode((instruction / 10000) % 10);
+        let i_type = InstructionType::try_from((instruction % 100) as i32)
+            .map_err(|_| Fault::InvalidOpcode(instruction % 100))?;
+        let p1_mode = self.parse_mode(((instruction / 100) % 10) as i32)?;
+        let p2_mode = self.parse_mode(((instruction / 1000) % 10) as i32)?;
+        let p3_mode = self.parse_mode(((instruction / 10000) % 10) as i32)?;
 
-        return Instruction {
+        return Ok(Instruction {
             i_type: i_type,
             p1_mode: p1_mode,
             p2_mode: p2_mode,
             p3_mode: p3_mode,
-        }
+        })
+    }
+
+    fn get_parameter(&self, i: usize) -> i64 {
+        return self.read_mem(self.ip + i);
     }
 
-    fn get_parameter(&self, i: usize) -> i32 {
-        return self.memory[self.ip + i];
+    fn get_parameter_with_mode(&self, i: usize, mode: ParameterMode) -> i64 {
+        return match mode {
+            ParameterMode::Memory => self.read_mem(self.read_mem(self.ip + i) as usize),
+            ParameterMode::Immediate => self.read_mem(self.ip + i),
+            ParameterMode::Relative => self.read_mem((self.relative_base + self.read_mem(self.ip + i)) as usize),
+        }
     }
 
-    fn get_parameter_with_mode(&self, i: usize, mode: ParameterMode) -> i32 {
+    fn get_store_address(&self, i: usize, mode: ParameterMode) -> usize {
         return match mode {
-            ParameterMode::Memory => self.memory[self.memory[self.ip + i] as usize],
-            ParameterMode::Immediate => self.memory[self.ip + i],
+            ParameterMode::Relative => (self.relative_base + self.get_parameter(i)) as usize,
+            _ => self.get_parameter(i) as usize,
         }
     }
 
-    fn parse_mode(&self, mode: i32) -> ParameterMode {
+    fn read_mem(&self, addr: usize) -> i64 {
+        return if addr < self.memory.len() {
+            self.memory[addr]
+        } else {
+            0
+        }
+    }
+
+    fn write_mem(&mut self, addr: usize, value: i64) {
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, 0);
+        }
+        self.memory[addr] = value;
+    }
+
+    fn parse_mode(&self, mode: i32) -> Result<ParameterMode, Fault> {
         return ParameterMode::try_from(mode)
-            .expect("invalid parameter mode");
+            .map_err(|_| Fault::InvalidMode(mode as i64));
+    }
+
+    /// Feed a value to the machine's input. This is how a caller supplies the
+    /// value a suspended `Read` needs after `run()` returned `NeedsInput`.
+    pub fn push_input(&mut self, v: i64) {
+        self.input.push(v);
+    }
+
+    /// A shared reference to the output sink, for reading back what a batched
+    /// or piped run produced.
+    pub fn output(&self) -> &O {
+        return &self.output;
+    }
+
+    /// Consume the processor, returning its input and output so a caller can
+    /// recover a wired `Pipe` or buffer once the machine has come to rest.
+    pub fn into_parts(self) -> (I, O) {
+        return (self.input, self.output);
+    }
+
+    /// The current instruction pointer.
+    pub fn ip(&self) -> usize {
+        return self.ip;
+    }
+
+    /// The current relative base.
+    pub fn relative_base(&self) -> i64 {
+        return self.relative_base;
+    }
+
+    /// Dump `len` cells of memory starting at `start`, reading beyond the
+    /// program image as the zero-initialized cells the VM would see.
+    pub fn memory_window(&self, start: usize, len: usize) -> Vec<i64> {
+        return (start..start + len).map(|addr| self.read_mem(addr)).collect();
+    }
+
+    /// Decode the word at `addr` into a printable `MNEMONIC arg, ...` form,
+    /// each argument rendered with its mode sigil (e.g. `ADD @4, #3, @5`).
+    pub fn disassemble(&self, addr: usize) -> Result<String, Fault> {
+        let instruction = self.decode(addr)?;
+        let modes = [instruction.p1_mode, instruction.p2_mode, instruction.p3_mode];
+
+        let params: Vec<String> = (0..instruction.i_type.arity())
+            .map(|i| format!("{}{}", modes[i].sigil(), self.read_mem(addr + 1 + i)))
+            .collect();
+
+        return Ok(if params.is_empty() {
+            instruction.i_type.mnemonic().to_string()
+        } else {
+            format!("{} {}", instruction.i_type.mnemonic(), params.join(", "))
+        })
+    }
+
+    /// Execute exactly one instruction, reporting whether the machine is still
+    /// running or has come to rest.
+    fn step_once(&mut self) -> Result<DebugState, Fault> {
+        return match self.step()? {
+            Flow::Continue => Ok(DebugState::Running),
+            Flow::Stop(RunState::Halted) => Ok(DebugState::Halted),
+            Flow::Stop(RunState::NeedsInput) => Ok(DebugState::NeedsInput),
+        }
+    }
+}
+
+/// The outcome of advancing a [`Debugger`].
+#[derive(Debug, PartialEq)]
+pub enum DebugState {
+    Running,
+    Breakpoint(usize),
+    Halted,
+    NeedsInput,
+}
+
+/// A single-step wrapper around a [`Processor`], tracking breakpoint addresses
+/// and handing control back to the caller whenever execution reaches one.
+pub struct Debugger<'a, I: Input, O: Output> {
+    processor: &'a mut Processor<I, O>,
+    breakpoints: HashSet<usize>,
+}
+
+impl<'a, I: Input, O: Output> Debugger<'a, I, O> {
+    pub fn new(processor: &'a mut Processor<I, O>) -> Debugger<'a, I, O> {
+        return Debugger {
+            processor: processor,
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    pub fn set_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn clear_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.remove(&addr);
+    }
+
+    /// Advance a single instruction.
+    pub fn step_once(&mut self) -> Result<DebugState, Fault> {
+        return self.processor.step_once();
+    }
+
+    /// Run until a breakpoint is reached or the machine comes to rest. One
+    /// instruction is always executed first, so calling this again while parked
+    /// on a breakpoint steps off it and continues rather than re-reporting the
+    /// same address forever.
+    pub fn continue_until_break(&mut self) -> Result<DebugState, Fault> {
+        match self.processor.step_once()? {
+            DebugState::Running => {},
+            state => return Ok(state),
+        }
+
+        loop {
+            if self.breakpoints.contains(&self.processor.ip) {
+                return Ok(DebugState::Breakpoint(self.processor.ip));
+            }
+
+            match self.processor.step_once()? {
+                DebugState::Running => {},
+                state => return Ok(state),
+            }
+        }
+    }
+
+    pub fn ip(&self) -> usize {
+        return self.processor.ip();
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        return self.processor.relative_base();
+    }
+
+    pub fn memory_window(&self, start: usize, len: usize) -> Vec<i64> {
+        return self.processor.memory_window(start, len);
+    }
+
+    /// Disassemble the word at `addr` via the underlying processor.
+    pub fn disassemble(&self, addr: usize) -> Result<String, Fault> {
+        return self.processor.disassemble(addr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(mut pipe: Pipe) -> Vec<i64> {
+        let mut out = Vec::new();
+        while let Some(v) = pipe.read() {
+            out.push(v);
+        }
+        return out;
+    }
+
+    // Run a program to completion in streaming mode, returning its output.
+    fn run(program: Vec<i64>, inputs: &[i64]) -> Vec<i64> {
+        let mut input = Pipe::new();
+        for &v in inputs {
+            input.push(v);
+        }
+
+        let mut processor = Processor::initialize(program, input, Pipe::new(), IoMode::Streaming);
+        assert_eq!(processor.run().unwrap(), RunState::Halted);
+
+        let (_input, output) = processor.into_parts();
+        return drain(output);
+    }
+
+    #[test]
+    fn relative_base_quine_reproduces_itself() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(run(program.clone(), &[]), program);
+    }
+
+    #[test]
+    fn handles_values_beyond_32_bits() {
+        // 34915192 * 34915192 is a 16-digit number, requiring 64-bit cells.
+        assert_eq!(
+            run(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0], &[]),
+            vec![1219070632396864],
+        );
+        assert_eq!(run(vec![104, 1125899906842624, 99], &[]), vec![1125899906842624]);
+    }
+
+    #[test]
+    fn suspends_and_resumes_around_missing_input() {
+        // Read two values, print their sum.
+        let program = vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+        let mut processor =
+            Processor::initialize(program, Pipe::new(), Pipe::new(), IoMode::Streaming);
+
+        assert_eq!(processor.run().unwrap(), RunState::NeedsInput);
+        processor.push_input(3);
+        assert_eq!(processor.run().unwrap(), RunState::NeedsInput);
+        processor.push_input(4);
+        assert_eq!(processor.run().unwrap(), RunState::Halted);
+
+        let (_input, output) = processor.into_parts();
+        assert_eq!(drain(output), vec![7]);
+    }
+
+    #[test]
+    fn wires_two_machines_through_a_shared_pipe() {
+        // Each stage doubles its single input.
+        let stage = vec![3, 9, 2, 9, 10, 9, 4, 9, 99, 0, 2];
+
+        let shared = Rc::new(RefCell::new(Pipe::new()));
+
+        let mut source = Pipe::new();
+        source.push(5);
+        let mut first = Processor::initialize(stage.clone(), source, shared.clone(), IoMode::Streaming);
+        assert_eq!(first.run().unwrap(), RunState::Halted);
+
+        let mut second = Processor::initialize(stage, shared, Pipe::new(), IoMode::Streaming);
+        assert_eq!(second.run().unwrap(), RunState::Halted);
+
+        let (_input, output) = second.into_parts();
+        assert_eq!(drain(output), vec![20]);
+    }
+
+    #[test]
+    fn debugger_disassembles_and_breaks() {
+        let program = vec![1, 5, 6, 7, 99, 3, 4, 0];
+        let mut processor =
+            Processor::initialize(program, Pipe::new(), Pipe::new(), IoMode::Streaming);
+        let mut debugger = Debugger::new(&mut processor);
+
+        assert_eq!(debugger.disassemble(0).unwrap(), "ADD @5, @6, @7");
+
+        debugger.set_breakpoint(4);
+        assert_eq!(debugger.continue_until_break().unwrap(), DebugState::Breakpoint(4));
+        assert_eq!(debugger.ip(), 4);
+        assert_eq!(debugger.memory_window(5, 3), vec![3, 4, 7]);
+
+        // A second call steps off the breakpoint and runs to the halt.
+        assert_eq!(debugger.continue_until_break().unwrap(), DebugState::Halted);
+    }
+
+    #[test]
+    fn batched_output_is_flushed_on_halt() {
+        let mut processor =
+            Processor::initialize(vec![104, 42, 104, 84, 99], Pipe::new(), Pipe::new(), IoMode::Batched);
+        assert_eq!(processor.run().unwrap(), RunState::Halted);
+
+        let (_input, output) = processor.into_parts();
+        assert_eq!(drain(output), vec![42, 84]);
+    }
+
+    #[test]
+    fn faults_are_returned_not_panicked() {
+        let mut processor =
+            Processor::initialize(vec![98], Pipe::new(), Pipe::new(), IoMode::Streaming);
+        assert_eq!(processor.run(), Err(Fault::InvalidOpcode(98)));
+
+        let mut bad_write =
+            Processor::initialize(vec![11101, 2, 3, 4, 99], Pipe::new(), Pipe::new(), IoMode::Streaming);
+        assert_eq!(bad_write.run(), Err(Fault::ImmediateWriteTarget));
+
+        assert_eq!(parse_program("1,x,3"), Err(Fault::MalformedProgram));
     }
 }